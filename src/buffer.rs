@@ -5,9 +5,11 @@
 //! This can result in additional copies and wasted space,
 //! however it guarantees that the data is always contiguous.
 
+use core::alloc::GlobalAlloc;
 use core::ptr::NonNull;
 
 use alloc::alloc::Layout;
+use alloc::vec::Vec;
 
 #[cfg(feature = "std")]
 fn copy_partial(output: &mut [u8], input: &[u8]) -> usize {
@@ -18,46 +20,157 @@ fn copy_partial(output: &mut [u8], input: &[u8]) -> usize {
     len
 }
 
+/// Returns the length of the longest prefix of `s` that is at most `max` bytes long
+/// and does not split a code point.
+fn utf8_prefix_len(s: &str, max: usize) -> usize {
+    if max >= s.len() {
+        return s.len();
+    }
+    // A code point is at most 4 bytes long, so this loop runs at most 4 times.
+    (0..=max).rev().find(|idx| s.is_char_boundary(*idx)).unwrap_or(0)
+}
+
+/// Lookup table for CRC-32 (IEEE 802.3, reflected polynomial `0xEDB88320`).
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Source of memory for a [`Buffer`].
+///
+/// This is implemented for [`Global`] and for every [`GlobalAlloc`],
+/// so custom allocators can implement `GlobalAlloc` instead of this trait.
+///
+/// # Safety
+/// Implementations must uphold the same contracts as the corresponding methods of
+/// [`GlobalAlloc`], and memory from one method must be usable with the others.
+pub unsafe trait Allocator {
+    /// Allocates zero-initialized memory, like [`GlobalAlloc::alloc_zeroed`].
+    ///
+    /// # Safety
+    /// See [`GlobalAlloc::alloc_zeroed`].
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8;
+    /// Deallocates memory, like [`GlobalAlloc::dealloc`].
+    ///
+    /// # Safety
+    /// See [`GlobalAlloc::dealloc`].
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+    /// Resizes memory, like [`GlobalAlloc::realloc`].
+    ///
+    /// # Safety
+    /// See [`GlobalAlloc::realloc`].
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8;
+}
+
+unsafe impl<T: GlobalAlloc> Allocator for T {
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        GlobalAlloc::alloc_zeroed(self, layout)
+    }
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        GlobalAlloc::dealloc(self, ptr, layout)
+    }
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        GlobalAlloc::realloc(self, ptr, layout, new_size)
+    }
+}
+
+/// The global allocator, as registered with `#[global_allocator]`.
+///
+/// This is the default allocator for [`Buffer`].
+/// It implements [`Allocator`] but not [`GlobalAlloc`],
+/// as registering it as the global allocator would make it call itself forever.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        alloc::alloc::alloc_zeroed(layout)
+    }
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc::alloc::dealloc(ptr, layout)
+    }
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        alloc::alloc::realloc(ptr, layout, new_size)
+    }
+}
+
 /// Linear resizeable byte buffer.
 ///
+/// Memory is obtained from `A`, which defaults to the [global allocator][Global].
+///
 /// Refer to the [module-level documentation][self] for more info.
 #[repr(C)]
-pub struct Buffer {
+pub struct Buffer<A: Allocator = Global> {
     bytes: NonNull<u8>,
+    /// Allocated size in bytes, plus the [`AUTO_COMPACT`] flag.
     capacity: usize,
     /// Right index: the start of the part of the buffer for input.
     input_idx: usize,
     /// Left index: the start of the part of the buffer for output.
     output_idx: usize,
+    alloc: A,
 }
 
-impl Drop for Buffer {
+/// Bit of [`Buffer`]'s `capacity` field that stores whether auto-compaction is enabled.
+///
+/// Allocations are never larger than `isize::MAX` bytes, so this bit is otherwise unused.
+/// Storing the flag here keeps `Buffer` the same size as before it existed.
+const AUTO_COMPACT: usize = !(isize::MAX as usize);
+
+#[cfg(test)]
+std::thread_local! {
+    /// Number of times [`Buffer::shift_to_start`] has moved data on the current thread.
+    static SHIFTS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+impl<A: Allocator> Drop for Buffer<A> {
     fn drop(&mut self) {
-        if self.capacity > 0 {
+        if self.capacity() > 0 {
             unsafe {
-                let layout = Layout::array::<u8>(self.capacity).unwrap();
-                alloc::alloc::dealloc(self.bytes.as_ptr(), layout);
+                let layout = Layout::array::<u8>(self.capacity()).unwrap();
+                self.alloc.dealloc(self.bytes.as_ptr(), layout);
             }
         }
     }
 }
 
-impl Clone for Buffer {
+impl<A: Allocator + Clone> Clone for Buffer<A> {
     fn clone(&self) -> Self {
-        let mut b = Self::with_capacity(self.capacity_min());
+        let mut b = Self::with_capacity_in(self.capacity_min(), self.alloc.clone());
+        b.set_auto_compact(self.auto_compact());
         let src = self.output_slice();
         let dest = b.input_slice_mut(src.len());
-        dest.copy_from_slice(src);
+        dest[..src.len()].copy_from_slice(src);
+        b.advance(src.len());
         b
     }
 }
 
-impl Default for Buffer {
+impl<A: Allocator + Default> Default for Buffer<A> {
     fn default() -> Self {
-        Self::new()
+        Self::new_in(A::default())
     }
 }
 
+/// Error for when memory could not be allocated,
+/// including when more than `isize::MAX` bytes would be required.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
 pub struct AllocFailure;
 
@@ -67,9 +180,52 @@ impl core::fmt::Display for AllocFailure {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for AllocFailure {}
+
+/// Error for when more bytes are requested than are available.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct CapacityError {
+    /// How many bytes were requested.
+    pub requested: usize,
+    /// How many bytes were available.
+    pub available: usize,
+}
+
+impl core::fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "requested {} bytes but only {} are available", self.requested, self.available)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+/// Outcome of a parse that can report how much more input it needs.
+///
+/// Used by [`BufferReader::parse_hinted`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ParseStatus<T, E> {
+    /// Parsing succeeded.
+    Done(T),
+    /// Parsing requires at least this many more bytes of input.
+    Incomplete(usize),
+    /// Parsing failed.
+    Err(E),
+}
+
+/// Line ending conventions.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Newline {
+    /// `\n`, as used by most UNIX-like systems.
+    Lf,
+    /// `\r\n`, as used by many text-based network protocols.
+    CrLf,
+}
+
 impl Buffer {
     pub const fn new() -> Self {
-        Buffer { bytes: NonNull::dangling(), capacity: 0, input_idx: 0, output_idx: 0 }
+        Self::new_in(Global)
     }
     /// Allocates a `Buffer` with a starting capacity that is at least `size` bytes.
     ///
@@ -77,10 +233,81 @@ impl Buffer {
     /// or if more bytes are requested than `isize::MAX`.
     /// Always verify the size of the input buffer before writing to it.
     pub fn with_capacity(capacity: usize) -> Buffer {
-        let mut this = Self::new();
+        Self::with_capacity_in(capacity, Global)
+    }
+    /// Allocates a `Buffer` with a starting capacity of exactly `capacity` bytes,
+    /// or returns an error if that allocation fails.
+    pub fn try_with_capacity(capacity: usize) -> Result<Buffer, AllocFailure> {
+        Self::try_with_capacity_in(capacity, Global)
+    }
+    /// Converts `self` into a `Vec` containing the output, reusing the allocation.
+    ///
+    /// The output is shifted to the start of the buffer if needed.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.shift_to_start();
+        let this = core::mem::ManuallyDrop::new(self);
+        // Both Buffer and Vec<u8> allocate from the global allocator
+        // using the layout of a `capacity`-length array of u8,
+        // and a dangling pointer is valid for a zero-capacity Vec.
+        unsafe { Vec::from_raw_parts(this.bytes.as_ptr(), this.input_idx, this.capacity()) }
+    }
+    /// Converts a `Vec` into a `Buffer` whose output is the `Vec`'s contents,
+    /// reusing the allocation.
+    ///
+    /// The `Vec`'s spare capacity is zeroed, as `Buffer` keeps its memory initialized.
+    pub fn from_vec(mut vec: Vec<u8>) -> Buffer {
+        vec.spare_capacity_mut().fill(core::mem::MaybeUninit::new(0));
+        let mut vec = core::mem::ManuallyDrop::new(vec);
+        let mut this = Buffer::new();
+        // Unwrap: Vec's pointer is never null, even when it has not allocated.
+        this.bytes = NonNull::new(vec.as_mut_ptr()).unwrap();
+        this.set_capacity(vec.capacity());
+        this.input_idx = vec.len();
+        this
+    }
+}
+
+impl From<Vec<u8>> for Buffer {
+    fn from(vec: Vec<u8>) -> Self {
+        Buffer::from_vec(vec)
+    }
+}
+
+impl From<Buffer> for Vec<u8> {
+    fn from(buffer: Buffer) -> Self {
+        buffer.into_vec()
+    }
+}
+
+impl<A: Allocator> Buffer<A> {
+    /// Creates an empty `Buffer` that will allocate from `alloc`.
+    pub const fn new_in(alloc: A) -> Self {
+        Buffer { bytes: NonNull::dangling(), capacity: 0, input_idx: 0, output_idx: 0, alloc }
+    }
+    /// Allocates a `Buffer` from `alloc` with a starting capacity
+    /// that is at least `size` bytes.
+    ///
+    /// The allocated capacity may be less than requested upon allocation failure
+    /// or if more bytes are requested than `isize::MAX`.
+    /// Always verify the size of the input buffer before writing to it.
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        let mut this = Self::new_in(alloc);
         this.realloc(capacity);
         this
     }
+    /// Allocates a `Buffer` from `alloc` with a starting capacity of exactly `capacity` bytes,
+    /// or returns an error if that allocation fails.
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, AllocFailure> {
+        let mut this = Self::new_in(alloc);
+        if capacity > isize::MAX as usize || !this.realloc(capacity) {
+            return Err(AllocFailure);
+        }
+        Ok(this)
+    }
+    /// Returns a reference to the allocator backing `self`.
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
     /// Returns true if there is no output available.
     pub fn is_empty(&self) -> bool {
         self.input_idx == self.output_idx
@@ -89,23 +316,55 @@ impl Buffer {
     ///
     /// This value may be more than the sum of available input and output bytes.
     pub fn capacity(&self) -> usize {
-        self.capacity
+        self.capacity & !AUTO_COMPACT
     }
     /// Returns how many bytes of space are available to read into.
     pub fn capacity_in(&self) -> usize {
-        self.capacity - self.input_idx
+        self.capacity() - self.input_idx
+    }
+    /// Returns how many bytes of space are unused before the output.
+    ///
+    /// This space is reclaimed by shifting the output to the start of the buffer,
+    /// which happens when more input space is needed than [`Buffer::capacity_in`] provides.
+    pub fn capacity_front(&self) -> usize {
+        self.output_idx
+    }
+    /// Returns true if the output starts at the very beginning of the buffer,
+    /// i.e. [`Buffer::capacity_front`] is 0.
+    pub fn is_front_aligned(&self) -> bool {
+        self.output_idx == 0
+    }
+    /// Returns whether automatic compaction is enabled.
+    ///
+    /// See [`Buffer::set_auto_compact`].
+    pub fn auto_compact(&self) -> bool {
+        self.capacity & AUTO_COMPACT != 0
+    }
+    /// Enables or disables automatic compaction. This is disabled by default.
+    ///
+    /// When enabled, reserving input space shifts the output to the start of the buffer
+    /// whenever more than half of the capacity precedes it,
+    /// even if there is already enough input space.
+    /// This trades a copy during writing for more contiguous input space,
+    /// which can avoid larger copies or reallocations later on.
+    pub fn set_auto_compact(&mut self, enabled: bool) {
+        if enabled {
+            self.capacity |= AUTO_COMPACT;
+        } else {
+            self.capacity &= !AUTO_COMPACT;
+        }
     }
     /// Returns how many bytes are available to read out of.
     pub fn len(&self) -> usize {
         self.input_idx - self.output_idx
     }
     /// Reborrows `self` as a [`BufferReader`], giving access to read operations.
-    pub fn reader(&mut self) -> &mut BufferReader {
-        unsafe { &mut *(self as *mut Self as *mut BufferReader) }
+    pub fn reader(&mut self) -> &mut BufferReader<A> {
+        unsafe { &mut *(self as *mut Self as *mut BufferReader<A>) }
     }
     /// Reborrows `self` as a [`BufferWriter`], giving access to write operations.
-    pub fn writer(&mut self) -> &mut BufferWriter {
-        unsafe { &mut *(self as *mut Self as *mut BufferWriter) }
+    pub fn writer(&mut self) -> &mut BufferWriter<A> {
+        unsafe { &mut *(self as *mut Self as *mut BufferWriter<A>) }
     }
     /// Shrinks `self`'s capacity to the size of the contained data or `min`, whichever is greater.
     ///
@@ -118,60 +377,88 @@ impl Buffer {
         let new_size = core::cmp::max(min, self.input_idx);
         self.realloc(new_size);
     }
+    /// Exchanges the contents and allocations of `self` and `other` without copying any bytes.
+    #[inline]
+    pub fn swap(&mut self, other: &mut Buffer<A>) {
+        core::mem::swap(self, other);
+    }
+    /// Replaces `self` with `new`, returning the old contents without copying any bytes.
+    #[inline]
+    pub fn replace(&mut self, new: Buffer<A>) -> Buffer<A> {
+        core::mem::replace(self, new)
+    }
     fn capacity_min(&self) -> usize {
-        self.capacity - self.output_idx
+        self.capacity() - self.output_idx
+    }
+    /// Sets the capacity without changing whether auto-compaction is enabled.
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity | (self.capacity & AUTO_COMPACT);
     }
     #[inline]
     /// # Safety
     /// Assumes that len will not be less than the right index of the buffer.
     fn realloc(&mut self, mut len: usize) -> bool {
-        use alloc::alloc::{alloc_zeroed, dealloc, realloc};
         len = core::cmp::min(len, isize::MAX as usize);
-        if len == self.capacity {
+        if len == self.capacity() {
             true
-        } else if self.capacity > 0 {
+        } else if self.capacity() > 0 {
             // Unwrap: something has gone horribly wrong if this isn't a valid layout.
-            let layout_old = Layout::array::<u8>(self.capacity).unwrap();
+            let layout_old = Layout::array::<u8>(self.capacity()).unwrap();
             if len > 0 {
-                let bytes = unsafe { realloc(self.bytes.as_ptr(), layout_old, len) };
+                let bytes = unsafe { self.alloc.realloc(self.bytes.as_ptr(), layout_old, len) };
                 let Some(bytes) = NonNull::new(bytes) else {
                     return false;
                 };
                 self.bytes = bytes;
-                if len > self.capacity {
+                if len > self.capacity() {
                     // Zero the new bytes, since realloc doesn't guarantee zero-init.
                     // Annoying that realloc_zeroed doesn't exist, since depending on the allocator,
                     // zeroing the memory can sometimes be redundant.
                     use core::ptr::write_bytes;
-                    let new_bytes = len - self.capacity;
-                    unsafe { write_bytes(self.bytes.as_ptr().add(self.capacity), 0, new_bytes) };
+                    let new_bytes = len - self.capacity();
+                    unsafe { write_bytes(self.bytes.as_ptr().add(self.capacity()), 0, new_bytes) };
                 }
             } else {
-                unsafe { dealloc(self.bytes.as_ptr(), layout_old) };
+                unsafe { self.alloc.dealloc(self.bytes.as_ptr(), layout_old) };
                 self.bytes = NonNull::dangling();
             }
-            self.capacity = len;
+            self.set_capacity(len);
             true
         } else {
             // Capacity is 0 and len != capacity (so len > 0).
             let Ok(layout) = Layout::array::<u8>(len) else {
                 return false;
             };
-            let Some(bytes) = NonNull::new(unsafe { alloc_zeroed(layout) }) else {
+            let Some(bytes) = NonNull::new(unsafe { self.alloc.alloc_zeroed(layout) }) else {
                 return false;
             };
             self.bytes = bytes;
-            self.capacity = len;
+            self.set_capacity(len);
             true
         }
     }
     fn reserve(&mut self, bytes: usize) -> bool {
-        if self.capacity_in() < bytes && self.shift_to_start() < bytes {
-            let new_capacity =
-                core::cmp::min(self.capacity + self.input_idx + bytes, isize::MAX as usize);
-            self.realloc(new_capacity)
+        self.try_reserve(bytes).is_ok()
+    }
+    fn try_reserve(&mut self, bytes: usize) -> Result<(), AllocFailure> {
+        if self.auto_compact() && self.output_idx > self.capacity() / 2 {
+            self.shift_to_start();
+        }
+        if self.capacity_in() >= bytes || self.shift_to_start() >= bytes {
+            return Ok(());
+        }
+        // The output is now at the start of the buffer, so input_idx == len().
+        let required = match self.input_idx.checked_add(bytes) {
+            Some(required) if required <= isize::MAX as usize => required,
+            _ => return Err(AllocFailure),
+        };
+        // Grow geometrically so that repeated small reservations are amortized.
+        // Saturate: realloc clamps to isize::MAX anyway.
+        let new_capacity = core::cmp::max(required, self.capacity().saturating_mul(2));
+        if self.realloc(new_capacity) {
+            Ok(())
         } else {
-            true
+            Err(AllocFailure)
         }
     }
     fn input_slice_mut(&mut self, min: usize) -> &mut [u8] {
@@ -182,6 +469,13 @@ impl Buffer {
     fn output_slice(&self) -> &[u8] {
         &self.full_slice()[self.output_idx..self.input_idx]
     }
+    /// Returns the length of the output up to and including the first `byte`,
+    /// or the length of the entire output if `byte` does not occur in it.
+    #[cfg(feature = "std")]
+    fn output_len_until(&self, byte: u8) -> usize {
+        let slice = self.output_slice();
+        slice.iter().position(|b| *b == byte).map_or(slice.len(), |idx| idx + 1)
+    }
     fn output_slice_mut(&mut self) -> &mut [u8] {
         // Conniptions, borrowck.
         let a = self.output_idx;
@@ -190,7 +484,11 @@ impl Buffer {
     }
     #[inline]
     fn consume(&mut self, count: usize) {
-        assert!(count <= self.len());
+        assert!(
+            count <= self.len(),
+            "tried to consume {count} bytes, but only {} are available for output",
+            self.len()
+        );
         self.output_idx += count;
         if self.is_empty() {
             self.output_idx = 0;
@@ -199,31 +497,37 @@ impl Buffer {
     }
     #[inline]
     fn advance(&mut self, count: usize) {
-        assert!(count <= self.capacity_in());
+        assert!(
+            count <= self.capacity_in(),
+            "tried to advance by {count} bytes, but only {} are available for input",
+            self.capacity_in()
+        );
         self.input_idx += count;
     }
     fn full_slice(&self) -> &[u8] {
-        unsafe { core::slice::from_raw_parts(self.bytes.as_ptr(), self.capacity) }
+        unsafe { core::slice::from_raw_parts(self.bytes.as_ptr(), self.capacity()) }
     }
     fn full_slice_mut(&mut self) -> &mut [u8] {
-        unsafe { core::slice::from_raw_parts_mut(self.bytes.as_mut(), self.capacity) }
+        unsafe { core::slice::from_raw_parts_mut(self.bytes.as_mut(), self.capacity()) }
     }
     /// Move all elements to the start in order to maximize input space.
     fn shift_to_start(&mut self) -> usize {
         if self.output_idx == 0 {
             return self.capacity_in();
         }
+        #[cfg(test)]
+        SHIFTS.with(|shifts| shifts.set(shifts.get() + 1));
         let range = self.output_idx..self.input_idx;
         let slice = self.full_slice_mut();
         slice.copy_within(range, 0);
-        let retval = self.capacity + self.output_idx - self.input_idx;
+        let retval = self.capacity() + self.output_idx - self.input_idx;
         self.input_idx -= self.output_idx;
         self.output_idx = 0;
         retval
     }
 }
 
-impl core::ops::Deref for Buffer {
+impl<A: Allocator> core::ops::Deref for Buffer<A> {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
@@ -231,14 +535,14 @@ impl core::ops::Deref for Buffer {
     }
 }
 
-impl core::ops::DerefMut for Buffer {
+impl<A: Allocator> core::ops::DerefMut for Buffer<A> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.output_slice_mut()
     }
 }
 
 #[cfg(feature = "std")]
-impl std::io::Read for Buffer {
+impl<A: Allocator> std::io::Read for Buffer<A> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         let len = copy_partial(buf, self.output_slice());
         self.consume(len);
@@ -248,17 +552,38 @@ impl std::io::Read for Buffer {
 }
 
 #[cfg(feature = "std")]
-impl std::io::BufRead for Buffer {
+impl<A: Allocator> std::io::BufRead for Buffer<A> {
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
         Ok(self.output_slice())
     }
     fn consume(&mut self, amt: usize) {
         self.consume(amt);
     }
+    // The output slice already contains everything that can be read,
+    // so these can copy out the entire result in one pass.
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        let len = self.output_len_until(byte);
+        buf.extend_from_slice(&self.output_slice()[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+    fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        let len = self.output_len_until(b'\n');
+        let line = core::str::from_utf8(&self.output_slice()[..len]).map(|line| buf.push_str(line));
+        // Consistent with the default impl, the line is consumed even if it's not UTF-8.
+        self.consume(len);
+        match line {
+            Ok(()) => Ok(len),
+            Err(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )),
+        }
+    }
 }
 
 #[cfg(feature = "std")]
-impl std::io::Write for Buffer {
+impl<A: Allocator> std::io::Write for Buffer<A> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let len = copy_partial(self.input_slice_mut(buf.len()), buf);
         self.advance(len);
@@ -273,10 +598,11 @@ impl std::io::Write for Buffer {
 /// Output interface to [`Buffer`].
 ///
 /// `Buffer`s can be used as this type with [`Buffer::reader`].
+/// Read-only accessors such as [`Buffer::len`] are available through [`Deref`][core::ops::Deref].
 #[repr(transparent)]
-pub struct BufferReader(Buffer);
+pub struct BufferReader<A: Allocator = Global>(Buffer<A>);
 
-impl BufferReader {
+impl<A: Allocator> BufferReader<A> {
     /// Returns a shared reference to a slice for reading out of.
     ///
     /// If a read opertion conceptually consumes bytes
@@ -286,6 +612,14 @@ impl BufferReader {
     pub fn slice(&self) -> &[u8] {
         self.0.output_slice()
     }
+    /// Returns the output as two slices, to be read in order.
+    ///
+    /// `Buffer` is always contiguous, so the second slice is always empty.
+    /// This exists so that code can handle both contiguous and wrapping buffers uniformly.
+    #[inline(always)]
+    pub fn as_two_slices(&self) -> (&[u8], &[u8]) {
+        (self.0.output_slice(), &[])
+    }
     /// Returns a mutable reference to a slice for reading out of.
     ///
     /// If a read opertion conceptually consumes bytes
@@ -304,12 +638,185 @@ impl BufferReader {
     pub fn consume(&mut self, count: usize) {
         self.0.consume(count);
     }
+    /// Returns true if the bytes of the output slice starting at `offset` begin with `needle`.
+    ///
+    /// Returns false if `offset + needle.len()` is past the end of the output slice.
+    #[inline]
+    pub fn matches_at(&self, offset: usize, needle: &[u8]) -> bool {
+        self.0.output_slice().get(offset..).map_or(false, |slice| slice.starts_with(needle))
+    }
+    /// Returns the offset just past the first occurrence of `needle` in the output slice,
+    /// or `None` if it does not occur.
+    ///
+    /// An empty `needle` is found at offset 0.
+    pub fn find_delimiter(&self, needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+        let pos = self.0.output_slice().windows(needle.len()).position(|w| w == needle)?;
+        Some(pos + needle.len())
+    }
+    /// Returns the first byte of the output slice without consuming it.
+    #[inline]
+    pub fn peek_first(&self) -> Option<u8> {
+        self.0.output_slice().first().copied()
+    }
+    /// Returns the last byte of the output slice without consuming it.
+    #[inline]
+    pub fn peek_last(&self) -> Option<u8> {
+        self.0.output_slice().last().copied()
+    }
+    /// Consumes and returns the first byte of the output slice.
+    #[inline]
+    pub fn split_first(&mut self) -> Option<u8> {
+        let byte = self.peek_first()?;
+        self.0.consume(1);
+        Some(byte)
+    }
+    /// Marks everything before `offset` in the output slice as having been read out of.
+    ///
+    /// This is equivalent to [`BufferReader::consume`], but reads better at call sites
+    /// that track absolute offsets of fields within the output slice.
+    ///
+    /// # Panics
+    /// Panics if `offset` is greater than the number of bytes available for output,
+    /// as this likely indicates a logic bug in the caller.
+    #[inline(always)]
+    pub fn advance_to(&mut self, offset: usize) {
+        self.0.consume(offset);
+    }
+    /// Marks `count` bytes of the front of the output slice as having been read out of,
+    /// or returns an error without modifying `self` if fewer than `count` bytes are available.
+    #[inline]
+    pub fn try_consume(&mut self, count: usize) -> Result<(), CapacityError> {
+        let available = self.0.len();
+        if count > available {
+            return Err(CapacityError { requested: count, available });
+        }
+        self.0.consume(count);
+        Ok(())
+    }
     /// Marks the entire output slice as having been read out of.
     #[inline(always)]
     pub fn consume_all(&mut self) {
         self.0.output_idx = 0;
         self.0.input_idx = 0;
     }
+    /// Consumes bytes from the front of the output slice for as long as `pred` returns true,
+    /// returning how many bytes were consumed.
+    ///
+    /// This stops at either the first byte that does not satisfy `pred` or the end of the
+    /// output slice. In the latter case, more matching bytes may follow once more input
+    /// is available.
+    pub fn consume_while(&mut self, mut pred: impl FnMut(u8) -> bool) -> usize {
+        let slice = self.0.output_slice();
+        let count = slice.iter().position(|b| !pred(*b)).unwrap_or(slice.len());
+        self.0.consume(count);
+        count
+    }
+    /// Returns how many bytes at the front of the output slice are equal to `byte`
+    /// without consuming any of them.
+    ///
+    /// If every byte in the output slice is equal to `byte`,
+    /// more may follow once more input is available.
+    pub fn count_leading(&self, byte: u8) -> usize {
+        let slice = self.0.output_slice();
+        slice.iter().position(|b| *b != byte).unwrap_or(slice.len())
+    }
+    /// Consumes bytes equal to `byte` from the front of the output slice,
+    /// returning how many bytes were consumed.
+    ///
+    /// See [`BufferReader::consume_while`] for caveats.
+    #[inline]
+    pub fn skip_leading(&mut self, byte: u8) -> usize {
+        self.consume_while(|b| b == byte)
+    }
+    /// Consumes ASCII whitespace from the front of the output slice,
+    /// returning how many bytes were consumed.
+    ///
+    /// See [`BufferReader::consume_while`] for caveats.
+    #[inline]
+    pub fn consume_whitespace(&mut self) -> usize {
+        self.consume_while(|b| b.is_ascii_whitespace())
+    }
+    /// Folds every byte of the output slice into an accumulator without consuming any of them.
+    pub fn fold<T>(&self, init: T, f: impl FnMut(T, u8) -> T) -> T {
+        self.0.output_slice().iter().copied().fold(init, f)
+    }
+    /// Computes the CRC-32 (as used by Ethernet, zlib, PNG, etc.) of the output slice
+    /// without consuming any of it.
+    pub fn crc32(&self) -> u32 {
+        !self.fold(!0u32, |crc, byte| (crc >> 8) ^ CRC32_TABLE[((crc as u8) ^ byte) as usize])
+    }
+    /// Computes the Adler-32 checksum (as used by zlib) of the output slice
+    /// without consuming any of it.
+    pub fn adler32(&self) -> u32 {
+        const MOD: u32 = 65521;
+        // Largest number of bytes that can be summed before `b` may overflow a u32.
+        const CHUNK: usize = 5552;
+        let (mut a, mut b) = (1u32, 0u32);
+        for chunk in self.0.output_slice().chunks(CHUNK) {
+            for byte in chunk {
+                a += *byte as u32;
+                b += a;
+            }
+            a %= MOD;
+            b %= MOD;
+        }
+        (b << 16) | a
+    }
+    /// Rewrites every line ending in the output slice to use the `to` convention.
+    ///
+    /// Both `\n` and `\r\n` are recognized as line endings; other `\r`s are left as-is.
+    /// Returns how many bytes at the front of the output slice are normalized,
+    /// which excludes a trailing `\r` as it may be the start of a `\r\n`
+    /// that has not been fully read in yet.
+    /// The trailing `\r` should not be consumed before calling this again with more input.
+    ///
+    /// Normalizing to [`Newline::CrLf`] may require more space,
+    /// and fails without modifying `self` if that space cannot be allocated.
+    pub fn normalize_newlines(&mut self, to: Newline) -> Result<usize, AllocFailure> {
+        match to {
+            Newline::Lf => {
+                let slice = self.0.output_slice_mut();
+                let len = slice.len();
+                let mut write = 0;
+                for read in 0..len {
+                    if slice[read] == b'\r' && slice.get(read + 1) == Some(&b'\n') {
+                        continue;
+                    }
+                    slice[write] = slice[read];
+                    write += 1;
+                }
+                self.0.input_idx -= len - write;
+            }
+            Newline::CrLf => {
+                let slice = self.0.output_slice();
+                let lfs = slice.iter().filter(|b| **b == b'\n').count();
+                let extra = lfs - slice.windows(2).filter(|w| *w == b"\r\n").count();
+                if extra > 0 {
+                    self.0.try_reserve(extra)?;
+                    // Work back-to-front so that bytes are never overwritten before being read.
+                    let start = self.0.output_idx;
+                    let end = self.0.input_idx;
+                    self.0.input_idx += extra;
+                    let slice = &mut self.0.full_slice_mut()[start..end + extra];
+                    let mut write = slice.len();
+                    for read in (0..end - start).rev() {
+                        let byte = slice[read];
+                        write -= 1;
+                        slice[write] = byte;
+                        if byte == b'\n' && (read == 0 || slice[read - 1] != b'\r') {
+                            write -= 1;
+                            slice[write] = b'\r';
+                        }
+                    }
+                }
+            }
+        }
+        let len = self.0.len();
+        Ok(if self.peek_last() == Some(b'\r') { len - 1 } else { len })
+    }
     /// Parses a value out of the output slice.
     ///
     /// Accepts a fallible closure that is expected to return both the parsed value and how many
@@ -328,6 +835,131 @@ impl BufferReader {
             Err(e) => Err(e),
         }
     }
+    /// Parses a value out of the output slice if enough input is available.
+    ///
+    /// Behaves like [`BufferReader::parse`], except that the closure may return
+    /// [`Poll::Pending`][core::task::Poll::Pending] to indicate that more input is needed,
+    /// which is passed through to the caller without consuming anything.
+    pub fn parse_incremental<'a, O, F, E>(&'a mut self, f: F) -> core::task::Poll<Result<O, E>>
+    where
+        O: 'a,
+        F: FnOnce(&'a [u8]) -> core::task::Poll<Result<(O, usize), E>>,
+    {
+        use core::task::Poll;
+        let slice = unsafe { core::slice::from_raw_parts(self.0.bytes.as_ptr(), self.0.input_idx) };
+        match f(&slice[self.0.output_idx..]) {
+            Poll::Ready(Ok((retval, consume))) => {
+                self.consume(consume);
+                Poll::Ready(Ok(retval))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+    /// Parses a value out of the output slice up to and including the first `needle`.
+    ///
+    /// Returns [`Poll::Pending`][core::task::Poll::Pending] without calling `f`
+    /// if `needle` is not in the output slice yet.
+    /// Otherwise, `f` is passed the output slice up to and including `needle`,
+    /// and that part of the output slice is consumed unless `f` returns an error.
+    pub fn parse_delimited<'a, O, F, E>(
+        &'a mut self,
+        needle: &[u8],
+        f: F,
+    ) -> core::task::Poll<Result<O, E>>
+    where
+        O: 'a,
+        F: FnOnce(&'a [u8]) -> Result<O, E>,
+    {
+        use core::task::Poll;
+        let Some(end) = self.find_delimiter(needle) else {
+            return Poll::Pending;
+        };
+        let slice = unsafe { core::slice::from_raw_parts(self.0.bytes.as_ptr(), self.0.input_idx) };
+        let retval = f(&slice[self.0.output_idx..][..end])?;
+        self.consume(end);
+        Poll::Ready(Ok(retval))
+    }
+    /// Parses a value out of the output slice, or reports how many more bytes are needed.
+    ///
+    /// Behaves like [`BufferReader::parse`], except that the closure may return
+    /// [`ParseStatus::Incomplete`] with how many more bytes it needs,
+    /// which is passed through to the caller without consuming anything.
+    /// The caller can use this to reserve enough space before reading more input.
+    pub fn parse_hinted<'a, O, F, E>(&'a mut self, f: F) -> ParseStatus<O, E>
+    where
+        O: 'a,
+        F: FnOnce(&'a [u8]) -> ParseStatus<(O, usize), E>,
+    {
+        let slice = unsafe { core::slice::from_raw_parts(self.0.bytes.as_ptr(), self.0.input_idx) };
+        match f(&slice[self.0.output_idx..]) {
+            ParseStatus::Done((retval, consume)) => {
+                self.consume(consume);
+                ParseStatus::Done(retval)
+            }
+            ParseStatus::Incomplete(needed) => ParseStatus::Incomplete(needed),
+            ParseStatus::Err(e) => ParseStatus::Err(e),
+        }
+    }
+    /// Parses as many owned records out of the output slice as possible, appending them to `out`.
+    ///
+    /// `f` is called repeatedly on the remaining output slice to parse one record,
+    /// until it returns [`ParseStatus::Incomplete`] or parses a record that consumes no bytes.
+    /// Returns how many records were parsed. If `f` returns [`ParseStatus::Err`],
+    /// records parsed before the error remain in `out` and consumed from `self`.
+    pub fn try_parse_many<O, F, E>(&mut self, out: &mut Vec<O>, mut f: F) -> Result<usize, E>
+    where
+        O: 'static,
+        F: FnMut(&[u8]) -> ParseStatus<(O, usize), E>,
+    {
+        let mut count = 0;
+        loop {
+            match f(self.0.output_slice()) {
+                ParseStatus::Done((record, consume)) => {
+                    self.consume(consume);
+                    out.push(record);
+                    count += 1;
+                    if consume == 0 {
+                        return Ok(count);
+                    }
+                }
+                ParseStatus::Incomplete(_) => return Ok(count),
+                ParseStatus::Err(e) => return Err(e),
+            }
+        }
+    }
+    /// Consumes and returns up to `size` bytes from the front of the output slice,
+    /// or returns `None` if the output slice is empty.
+    ///
+    /// Repeatedly calling this splits the output slice into `size`-byte chunks,
+    /// the last of which may be shorter.
+    ///
+    /// # Panics
+    /// Panics if `size` is 0.
+    pub fn next_chunk(&mut self, size: usize) -> Option<&[u8]> {
+        assert!(size != 0, "chunk size must be non-zero");
+        self.parse(|slice| match slice.len() {
+            0 => Err(()),
+            len => {
+                let len = core::cmp::min(len, size);
+                Ok((&slice[..len], len))
+            }
+        })
+        .ok()
+    }
+    /// Parses an owned value out of the output slice.
+    ///
+    /// Behaves like [`BufferReader::parse`], except that the parsed value cannot borrow
+    /// from the buffer, so `self` is free to be used again as soon as this returns.
+    pub fn parse_owned<O, F, E>(&mut self, f: F) -> Result<O, E>
+    where
+        O: 'static,
+        F: FnOnce(&[u8]) -> Result<(O, usize), E>,
+    {
+        let (retval, consume) = f(self.0.output_slice())?;
+        self.consume(consume);
+        Ok(retval)
+    }
     #[cfg(feature = "std")]
     /// Writes data to a provided [`std::io::Write`].
     #[inline(always)]
@@ -336,18 +968,54 @@ impl BufferReader {
         self.0.consume(count);
         Ok(count)
     }
+    #[cfg(feature = "std")]
+    /// Writes all data to a provided [`std::io::Write`], retrying on short writes.
+    ///
+    /// Returns how many bytes were written. Upon error, some data may have been written
+    /// and consumed.
+    pub fn write_to_all<T: std::io::Write>(&mut self, write: &mut T) -> std::io::Result<usize> {
+        let mut total = 0;
+        while !self.0.is_empty() {
+            match self.write_to(write) {
+                Ok(0) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                Ok(count) => total += count,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(total)
+    }
 }
 
-impl core::ops::Deref for BufferReader {
-    type Target = Buffer;
+impl<A: Allocator> core::ops::Deref for BufferReader<A> {
+    type Target = Buffer<A>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
+/// Formats the output slice as lowercase hex digits, two per byte.
+impl<A: Allocator> core::fmt::LowerHex for BufferReader<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.output_slice().iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Formats the output slice as uppercase hex digits, two per byte.
+impl<A: Allocator> core::fmt::UpperHex for BufferReader<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.output_slice().iter().try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
+
 #[cfg(feature = "std")]
-impl std::io::Read for BufferReader {
+impl<A: Allocator> std::io::Read for BufferReader<A> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.0.read(buf)
     }
@@ -355,22 +1023,43 @@ impl std::io::Read for BufferReader {
 }
 
 #[cfg(feature = "std")]
-impl std::io::BufRead for BufferReader {
+impl<A: Allocator> std::io::BufRead for BufferReader<A> {
     fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
         self.0.fill_buf()
     }
     fn consume(&mut self, amt: usize) {
         self.0.consume(amt)
     }
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> std::io::Result<usize> {
+        self.0.read_until(byte, buf)
+    }
+    fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        self.0.read_line(buf)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<A: Allocator> bytes::Buf for BufferReader<A> {
+    fn remaining(&self) -> usize {
+        self.0.len()
+    }
+    fn chunk(&self) -> &[u8] {
+        self.0.output_slice()
+    }
+    fn advance(&mut self, cnt: usize) {
+        self.0.consume(cnt);
+    }
 }
 
 /// Input interface to [`Buffer`].
 ///
 /// `Buffer`s can be used as this type with [`Buffer::writer`].
+/// Read-only accessors such as [`Buffer::capacity_in`] are available
+/// through [`Deref`][core::ops::Deref].
 #[repr(transparent)]
-pub struct BufferWriter(Buffer);
+pub struct BufferWriter<A: Allocator = Global>(Buffer<A>);
 
-impl BufferWriter {
+impl<A: Allocator> BufferWriter<A> {
     /// Returns a mutable reference to a slice for writing to.
     /// The slice will be at least `min` bytes long,
     /// except in cases of allocation failure or more than `isize::MAX`
@@ -383,6 +1072,78 @@ impl BufferWriter {
     pub fn slice_mut(&mut self, min: usize) -> &mut [u8] {
         self.0.input_slice_mut(min)
     }
+    /// Returns a mutable reference to exactly `len` zeroed bytes at the front of the input slice.
+    ///
+    /// Unlike [`BufferWriter::slice_mut`], the returned slice never contains stale bytes
+    /// from earlier writes. The slice may be shorter than `len` in the same cases that
+    /// `slice_mut` may be shorter than `min`.
+    ///
+    /// After writing, [`BufferWriter::advance`] should be called
+    /// with how many bytes have been written.
+    pub fn zeroed_slice(&mut self, len: usize) -> &mut [u8] {
+        let slice = self.0.input_slice_mut(len);
+        let len = core::cmp::min(len, slice.len());
+        let slice = &mut slice[..len];
+        slice.fill(0);
+        slice
+    }
+    /// Passes `n` zeroed bytes of input space to `f` to be filled, then marks them as read into.
+    ///
+    /// Returns how many bytes were committed,
+    /// which is less than `n` only when [`BufferWriter::zeroed_slice`] would be short.
+    pub fn commit_from(&mut self, n: usize, f: impl FnOnce(&mut [u8])) -> usize {
+        let slice = self.zeroed_slice(n);
+        let len = slice.len();
+        f(slice);
+        self.0.advance(len);
+        len
+    }
+    /// Passes up to `n` zeroed bytes of input space to `f` to be filled,
+    /// then marks as many bytes as `f` reports having written as read into.
+    ///
+    /// If `f` returns an error, nothing is marked as read into.
+    ///
+    /// # Panics
+    /// Panics if `f` reports writing more bytes than it was given.
+    pub fn try_commit_from<E>(
+        &mut self,
+        n: usize,
+        f: impl FnOnce(&mut [u8]) -> Result<usize, E>,
+    ) -> Result<usize, E> {
+        let slice = self.zeroed_slice(n);
+        let len = slice.len();
+        let written = f(slice)?;
+        assert!(written <= len, "wrote {written} bytes into a {len}-byte slice");
+        self.0.advance(written);
+        Ok(written)
+    }
+    /// Returns a mutable reference to a slice for writing to whose address is aligned to `align`.
+    /// The slice will be exactly `len` bytes long, or empty if that much space
+    /// plus any padding cannot be reserved, in which case no padding is added.
+    ///
+    /// To align the slice, up to `align - 1` padding bytes are marked as read into,
+    /// which makes them available at the end of the output slice.
+    /// The padding bytes are zeroed.
+    ///
+    /// After writing, [`BufferWriter::advance`] should be called
+    /// with how many bytes have been written.
+    /// The alignment is only guaranteed until the buffer is next modified.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two.
+    pub fn reserve_aligned(&mut self, len: usize, align: usize) -> &mut [u8] {
+        assert!(align.is_power_of_two(), "alignment must be a power of two");
+        self.0.reserve(len.saturating_add(align - 1));
+        let addr = self.0.bytes.as_ptr() as usize + self.0.input_idx;
+        let padding = addr.wrapping_neg() & (align - 1);
+        if padding.checked_add(len).map_or(true, |total| total > self.0.capacity_in()) {
+            return &mut [];
+        }
+        self.0.input_slice_mut(0)[..padding].fill(0);
+        self.0.advance(padding);
+        // No more space is needed, so this won't move the data.
+        &mut self.0.input_slice_mut(0)[..len]
+    }
     /// Marks `count` bytes of the front of the input slice as having been read into,
     /// making them available at the end of the output slice.
     ///
@@ -393,15 +1154,86 @@ impl BufferWriter {
     pub fn advance(&mut self, count: usize) {
         self.0.advance(count);
     }
-    /// Ensures that at least `bytes` bytes are available for input to the buffer.
+    /// Marks `count` bytes of the front of the input slice as having been read into,
+    /// or returns an error without modifying `self` if fewer than `count` bytes are available.
+    #[inline]
+    pub fn try_advance(&mut self, count: usize) -> Result<(), CapacityError> {
+        let available = self.0.capacity_in();
+        if count > available {
+            return Err(CapacityError { requested: count, available });
+        }
+        self.0.advance(count);
+        Ok(())
+    }
+    /// Marks `count` bytes as having been read into like [`BufferWriter::advance`],
+    /// then returns a shared reference to those bytes at the end of the output slice.
     ///
     /// # Panics
-    /// Panics if the total size of the buffer would exceed `isize::MAX`
-    /// as a result of this operation.
+    /// Panics if `count` is greater than the number of bytes available for input,
+    /// as this likely indicates a logic bug in the caller.
+    #[inline]
+    pub fn advance_and_slice(&mut self, count: usize) -> &[u8] {
+        self.0.advance(count);
+        let slice = self.0.output_slice();
+        &slice[slice.len() - count..]
+    }
+    /// Ensures there is enough input space to hold the rest of the next message,
+    /// given a hint of how long that message is in total.
+    ///
+    /// If `frame_len` is `Some`, this reserves enough space for the bytes of the message that
+    /// are not yet in the output slice, so that a single read can complete the message.
+    /// Otherwise, this reserves space for a read of a reasonable default size.
+    pub fn reserve_for_read(&mut self, frame_len: Option<usize>) {
+        /// How many bytes to reserve when the length of the next message is unknown.
+        const DEFAULT_READ_SIZE: usize = 4096;
+        let bytes = match frame_len {
+            Some(frame_len) => frame_len.saturating_sub(self.0.len()),
+            None => DEFAULT_READ_SIZE,
+        };
+        self.0.reserve(bytes);
+    }
+    /// Writes as much of `s` as fits in the input slice without splitting a code point,
+    /// returning how many bytes were written.
+    ///
+    /// This reserves space for all of `s`, so it is only short upon allocation failure
+    /// or if more than `isize::MAX` bytes of capacity would be required.
+    /// The written bytes are always valid UTF-8.
+    #[must_use = "the string may have only been partially written"]
+    pub fn write_str(&mut self, s: &str) -> usize {
+        let slice = self.0.input_slice_mut(s.len());
+        let len = utf8_prefix_len(s, slice.len());
+        slice[..len].copy_from_slice(&s.as_bytes()[..len]);
+        self.0.advance(len);
+        len
+    }
+    /// Writes `count` copies of `byte`, returning how many were written.
+    ///
+    /// This is only short upon allocation failure
+    /// or if more than `isize::MAX` bytes of capacity would be required.
+    #[must_use = "fewer bytes than requested may have been written"]
+    pub fn write_repeated(&mut self, byte: u8, count: usize) -> usize {
+        let slice = self.0.input_slice_mut(count);
+        let count = core::cmp::min(count, slice.len());
+        slice[..count].fill(byte);
+        self.0.advance(count);
+        count
+    }
+    /// Ensures that at least `bytes` bytes are available for input to the buffer.
+    ///
+    /// Less space may be available upon allocation failure
+    /// or if the total size of the buffer would exceed `isize::MAX`.
+    /// Otherwise, [`Buffer::capacity_in`] is at least `bytes` afterward.
+    /// If enough space is already available, this never allocates.
     #[inline(always)]
     pub fn reserve(&mut self, bytes: usize) {
         self.0.reserve(bytes);
     }
+    /// Ensures that at least `bytes` bytes are available for input to the buffer,
+    /// or returns an error without losing any data if that is not possible.
+    #[inline(always)]
+    pub fn try_reserve(&mut self, bytes: usize) -> Result<(), AllocFailure> {
+        self.0.try_reserve(bytes)
+    }
     #[cfg(feature = "std")]
     /// Reads data once from a provided [`std::io::Read`].
     pub fn read_from<T: std::io::Read>(
@@ -413,18 +1245,88 @@ impl BufferWriter {
         self.advance(count);
         Ok(count)
     }
+    #[cfg(feature = "std")]
+    /// Reads data from a provided [`std::io::Read`] until it reaches EOF.
+    ///
+    /// Each read is made with at least `min` bytes of input space, and never with none.
+    /// Returns how many bytes were read. Upon error, some data may have been read.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`std::io::ErrorKind::OutOfMemory`]
+    /// if no input space could be reserved before reaching EOF.
+    pub fn read_from_all<T: std::io::Read>(
+        &mut self,
+        min: usize,
+        read: &mut T,
+    ) -> std::io::Result<usize> {
+        let min = core::cmp::max(min, 1);
+        let mut total = 0;
+        loop {
+            let slice = self.0.input_slice_mut(min);
+            if slice.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::OutOfMemory,
+                    "failed to reserve space to read into",
+                ));
+            }
+            match read.read(slice) {
+                Ok(0) => return Ok(total),
+                Ok(count) => {
+                    self.0.advance(count);
+                    total += count;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
 }
 
-impl core::ops::Deref for BufferWriter {
-    type Target = Buffer;
+impl<A: Allocator> core::ops::Deref for BufferWriter<A> {
+    type Target = Buffer<A>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
+/// Writes formatted text into the buffer.
+///
+/// Unlike [`BufferWriter::write_str`], strings are never partially written.
+/// Space for each string is reserved up front, and if that fails, an error is returned instead.
+impl<A: Allocator> core::fmt::Write for BufferWriter<A> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0.try_reserve(s.len()).map_err(|_| core::fmt::Error)?;
+        self.0.input_slice_mut(s.len())[..s.len()].copy_from_slice(s.as_bytes());
+        self.0.advance(s.len());
+        Ok(())
+    }
+}
+
+// Like Vec, BufferWriter grows on demand,
+// so remaining_mut reports how much it could grow rather than the current input space.
+#[cfg(feature = "bytes")]
+unsafe impl<A: Allocator> bytes::BufMut for BufferWriter<A> {
+    fn remaining_mut(&self) -> usize {
+        isize::MAX as usize - self.0.len()
+    }
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        self.0.advance(cnt);
+    }
+    fn chunk_mut(&mut self) -> &mut bytes::buf::UninitSlice {
+        /// How much input space to ensure is available if there is none.
+        const MIN_CHUNK: usize = 64;
+        // Returning an empty chunk while remaining_mut is nonzero would make callers spin,
+        // so failing to grow is fatal here, as it is for Vec.
+        if self.0.capacity_in() == 0 && self.0.try_reserve(MIN_CHUNK).is_err() {
+            panic!("failed to reserve {MIN_CHUNK} bytes of input space");
+        }
+        bytes::buf::UninitSlice::new(self.0.input_slice_mut(0))
+    }
+}
+
 #[cfg(feature = "std")]
-impl std::io::Write for BufferWriter {
+impl<A: Allocator> std::io::Write for BufferWriter<A> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.0.write(buf)
     }
@@ -436,7 +1338,55 @@ impl std::io::Write for BufferWriter {
 
 #[cfg(test)]
 mod tests {
-    use super::Buffer;
+    use super::{utf8_prefix_len, AllocFailure, Buffer, CapacityError, Newline, ParseStatus};
+    use alloc::alloc::Layout;
+    use alloc::vec::Vec;
+    use core::alloc::GlobalAlloc;
+    use core::cell::Cell;
+
+    /// Allocator that counts calls into it before forwarding them to the global allocator.
+    ///
+    /// Buffers allocate from a shared reference to it,
+    /// so the counts can be checked while they are still alive.
+    #[derive(Default)]
+    struct Tracking {
+        allocs: Cell<usize>,
+        reallocs: Cell<usize>,
+        deallocs: Cell<usize>,
+    }
+
+    impl Tracking {
+        /// Returns how many allocations, reallocations, and deallocations there have been.
+        fn counts(&self) -> (usize, usize, usize) {
+            (self.allocs.get(), self.reallocs.get(), self.deallocs.get())
+        }
+    }
+
+    unsafe impl GlobalAlloc for &Tracking {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            self.allocs.set(self.allocs.get() + 1);
+            alloc::alloc::alloc(layout)
+        }
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            self.allocs.set(self.allocs.get() + 1);
+            alloc::alloc::alloc_zeroed(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.deallocs.set(self.deallocs.get() + 1);
+            alloc::alloc::dealloc(ptr, layout)
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            self.reallocs.set(self.reallocs.get() + 1);
+            alloc::alloc::realloc(ptr, layout, new_size)
+        }
+    }
+
+    fn with_contents(bytes: &[u8]) -> Buffer {
+        let mut buffer = Buffer::with_capacity(bytes.len());
+        buffer.writer().slice_mut(bytes.len())[..bytes.len()].copy_from_slice(bytes);
+        buffer.writer().advance(bytes.len());
+        buffer
+    }
 
     #[test]
     fn zero_capacity() {
@@ -445,6 +1395,703 @@ mod tests {
         buffer.input_slice_mut(64);
         assert!(buffer.capacity_in() >= 64);
     }
+    #[test]
+    fn clone_partially_consumed() {
+        let mut buffer = with_contents(b"headerpayload");
+        buffer.reader().consume(6);
+        let mut clone = buffer.clone();
+        assert_eq!(&clone[..], b"payload");
+        assert_eq!(clone.capacity(), 7);
+        assert!(clone.is_front_aligned());
+        clone.reader().consume(3);
+        assert_eq!(&clone[..], b"load");
+        assert_eq!(&buffer[..], b"payload");
+    }
+    #[test]
+    fn advance_and_slice() {
+        let mut buffer = Buffer::with_capacity(16);
+        buffer.writer().slice_mut(3)[..3].copy_from_slice(b"abc");
+        buffer.writer().advance(3);
+        buffer.writer().slice_mut(4)[..4].copy_from_slice(b"defg");
+        assert_eq!(buffer.writer().advance_and_slice(4), b"defg");
+        assert_eq!(&buffer[..], b"abcdefg");
+    }
+    #[test]
+    fn swap() {
+        let mut a = Buffer::with_capacity(8);
+        a.writer().slice_mut(3)[..3].copy_from_slice(b"foo");
+        a.writer().advance(3);
+        let mut b = Buffer::with_capacity(32);
+        b.writer().slice_mut(6)[..6].copy_from_slice(b"barbaz");
+        b.writer().advance(6);
+        a.swap(&mut b);
+        assert_eq!(&a[..], b"barbaz");
+        assert_eq!(a.capacity(), 32);
+        assert_eq!(&b[..], b"foo");
+        assert_eq!(b.capacity(), 8);
+        let old = a.replace(Buffer::new());
+        assert!(a.is_empty());
+        assert_eq!(a.capacity(), 0);
+        assert_eq!(&old[..], b"barbaz");
+    }
+    #[test]
+    fn parse_owned() {
+        #[derive(PartialEq, Eq, Debug)]
+        struct Header {
+            kind: u8,
+            len: u16,
+        }
+        let mut buffer = Buffer::with_capacity(8);
+        buffer.writer().slice_mut(4)[..4].copy_from_slice(&[7, 0, 2, 0xAA]);
+        buffer.writer().advance(4);
+        let header = buffer
+            .reader()
+            .parse_owned(|bytes| match bytes {
+                [kind, a, b, ..] => {
+                    Ok((Header { kind: *kind, len: u16::from_be_bytes([*a, *b]) }, 3))
+                }
+                _ => Err(()),
+            })
+            .unwrap();
+        // The buffer is no longer borrowed by `header`.
+        buffer.writer().slice_mut(1)[0] = 0xBB;
+        buffer.writer().advance(1);
+        assert_eq!(header, Header { kind: 7, len: 2 });
+        assert_eq!(&buffer[..], &[0xAA, 0xBB]);
+        assert!(buffer.reader().parse_owned(|_| Err::<((), usize), _>(())).is_err());
+        assert_eq!(buffer.len(), 2);
+    }
+    #[test]
+    fn consume_whitespace() {
+        let mut buffer = with_contents(b" \t\r\n  ok");
+        assert_eq!(buffer.reader().consume_whitespace(), 6);
+        assert_eq!(&buffer[..], b"ok");
+        assert_eq!(buffer.reader().consume_whitespace(), 0);
+        assert_eq!(buffer.reader().consume_while(|b| b == b'o'), 1);
+        assert_eq!(&buffer[..], b"k");
+    }
+    #[test]
+    fn consume_whitespace_all() {
+        let mut buffer = with_contents(b"    ");
+        assert_eq!(buffer.reader().consume_whitespace(), 4);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.reader().consume_whitespace(), 0);
+    }
+    #[test]
+    fn capacity_front() {
+        let mut buffer = with_contents(b"abcdef");
+        assert_eq!(buffer.capacity_front(), 0);
+        buffer.reader().consume(2);
+        assert_eq!(buffer.capacity_front(), 2);
+        assert_eq!(
+            buffer.capacity_front() + buffer.len() + buffer.capacity_in(),
+            buffer.capacity()
+        );
+        buffer.reader().consume(4);
+        assert_eq!(buffer.capacity_front(), 0);
+    }
+    #[test]
+    fn checksums() {
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.reader().crc32(), 0);
+        assert_eq!(buffer.reader().adler32(), 1);
+        let mut buffer = with_contents(b"123456789");
+        assert_eq!(buffer.reader().crc32(), 0xCBF43926);
+        assert_eq!(buffer.reader().fold(0u32, |sum, b| sum + b as u32), 477);
+        assert_eq!(buffer.len(), 9);
+        let mut buffer = with_contents(b"Wikipedia");
+        assert_eq!(buffer.reader().adler32(), 0x11E60398);
+        let mut buffer = with_contents(&[0xFF; 10000]);
+        assert_eq!(buffer.reader().adler32(), 0xB623EB2B);
+    }
+    #[test]
+    fn try_advance_consume() {
+        let mut buffer = Buffer::with_capacity(8);
+        let err = buffer.writer().try_advance(9).unwrap_err();
+        assert_eq!(err, CapacityError { requested: 9, available: 8 });
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.writer().try_advance(5), Ok(()));
+        let err = buffer.reader().try_consume(6).unwrap_err();
+        assert_eq!(err, CapacityError { requested: 6, available: 5 });
+        assert_eq!(buffer.len(), 5);
+        assert_eq!(buffer.reader().try_consume(5), Ok(()));
+        assert!(buffer.is_empty());
+    }
+    #[test]
+    fn custom_allocator() {
+        let tracking = Tracking::default();
+        {
+            let mut buffer = Buffer::with_capacity_in(8, &tracking);
+            assert_eq!(tracking.counts(), (1, 0, 0));
+            buffer.writer().slice_mut(16)[..16].copy_from_slice(&[0xAB; 16]);
+            buffer.writer().advance(16);
+            assert_eq!(tracking.counts(), (1, 1, 0));
+            let clone = buffer.clone();
+            assert_eq!(&clone[..], &buffer[..]);
+            assert_eq!(tracking.counts(), (2, 1, 0));
+        }
+        assert_eq!(tracking.counts(), (2, 1, 2));
+        let mut buffer = Buffer::new_in(&tracking);
+        buffer.shrink_to_fit(0);
+        drop(buffer);
+        assert_eq!(tracking.counts(), (2, 1, 2));
+    }
+    #[test]
+    fn reserve_after_partial_consume() {
+        let mut buffer = with_contents(b"abcdefgh");
+        buffer.reader().consume(5);
+        buffer.writer().reserve(10);
+        assert!(buffer.capacity_in() >= 10);
+        assert!(buffer.capacity() >= buffer.len() + 10);
+        assert_eq!(&buffer[..], b"fgh");
+        // Must not overflow, even though the allocation will fail.
+        buffer.writer().reserve(usize::MAX);
+        assert_eq!(&buffer[..], b"fgh");
+    }
+    #[test]
+    fn split_first() {
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.reader().peek_first(), None);
+        assert_eq!(buffer.reader().peek_last(), None);
+        assert_eq!(buffer.reader().split_first(), None);
+        let mut buffer = with_contents(b"xyz");
+        assert_eq!(buffer.reader().peek_first(), Some(b'x'));
+        assert_eq!(buffer.reader().peek_last(), Some(b'z'));
+        assert_eq!(buffer.reader().split_first(), Some(b'x'));
+        assert_eq!(&buffer[..], b"yz");
+    }
+    #[test]
+    fn zeroed_slice() {
+        let mut buffer = with_contents(b"stale");
+        buffer.reader().consume_all();
+        let slice = buffer.writer().zeroed_slice(4);
+        assert_eq!(slice, &[0; 4]);
+        slice[..3].copy_from_slice(b"new");
+        buffer.writer().advance(3);
+        assert_eq!(&buffer[..], b"new");
+    }
+    #[test]
+    fn hex() {
+        use alloc::format;
+        let mut buffer = with_contents(&[0x00, 0x1F, 0xAB, 0xFF]);
+        assert_eq!(format!("{:x}", buffer.reader()), "001fabff");
+        assert_eq!(format!("{:X}", buffer.reader()), "001FABFF");
+        buffer.reader().consume_all();
+        assert_eq!(format!("{:x}", buffer.reader()), "");
+    }
+    #[test]
+    fn as_two_slices() {
+        let mut buffer = with_contents(b"abcd");
+        buffer.reader().consume(1);
+        assert_eq!(buffer.reader().as_two_slices(), (&b"bcd"[..], &b""[..]));
+    }
+    #[test]
+    fn auto_compact() {
+        let shifts = || super::SHIFTS.with(|shifts| shifts.get());
+        assert_eq!(core::mem::size_of::<Buffer>(), 4 * core::mem::size_of::<usize>());
+        let mut buffer = with_contents(b"0123456789");
+        assert!(!buffer.auto_compact());
+        buffer.reader().consume(6);
+        buffer.writer().reserve(0);
+        assert_eq!(buffer.capacity_front(), 6);
+        assert_eq!(shifts(), 0);
+
+        let mut buffer = with_contents(b"0123456789");
+        buffer.set_auto_compact(true);
+        assert_eq!(buffer.capacity(), 10);
+        buffer.reader().consume(5);
+        buffer.writer().reserve(0);
+        assert_eq!(buffer.capacity_front(), 5);
+        assert_eq!(shifts(), 0);
+        // Consuming never moves the output, since it may still be borrowed.
+        buffer.reader().consume(1);
+        assert_eq!(buffer.capacity_front(), 6);
+        assert_eq!(shifts(), 0);
+        buffer.writer().reserve(0);
+        assert_eq!(shifts(), 1);
+        assert!(buffer.is_front_aligned());
+        assert_eq!(&buffer[..], b"6789");
+        assert_eq!(buffer.capacity_in(), 6);
+        assert!(buffer.clone().auto_compact());
+        buffer.set_auto_compact(false);
+        assert!(!buffer.auto_compact());
+        assert_eq!(buffer.capacity(), 10);
+        assert_eq!(shifts(), 1);
+    }
+    #[test]
+    fn auto_compact_borrowed_output() {
+        use core::task::Poll;
+        let mut buffer = with_contents(b"0123456789");
+        buffer.set_auto_compact(true);
+        assert_eq!(buffer.reader().next_chunk(6), Some(&b"012345"[..]));
+        let mut buffer = with_contents(b"NICK foo\r\nPING");
+        buffer.set_auto_compact(true);
+        let r = buffer.reader().parse_delimited(b"\r\n", Ok::<_, ()>);
+        assert_eq!(r, Poll::Ready(Ok(&b"NICK foo\r\n"[..])));
+        assert_eq!(&buffer[..], b"PING");
+    }
+    #[test]
+    fn skip_leading() {
+        let mut buffer = with_contents(b"\0\0\0data");
+        assert_eq!(buffer.reader().count_leading(0), 3);
+        assert_eq!(buffer.len(), 7);
+        assert_eq!(buffer.reader().skip_leading(0), 3);
+        assert_eq!(&buffer[..], b"data");
+        assert_eq!(buffer.reader().count_leading(0), 0);
+        assert_eq!(buffer.reader().skip_leading(0), 0);
+        let mut buffer = with_contents(&[0xFF; 5]);
+        assert_eq!(buffer.reader().count_leading(0xFF), 5);
+        assert_eq!(buffer.reader().skip_leading(0xFF), 5);
+        assert!(buffer.is_empty());
+    }
+    #[test]
+    fn reserve_for_read() {
+        let mut buffer = with_contents(b"hdr");
+        buffer.writer().reserve_for_read(Some(100_000));
+        assert!(buffer.capacity_in() >= 100_000 - 3);
+        let mut buffer = Buffer::new();
+        buffer.writer().reserve_for_read(None);
+        assert!(buffer.capacity_in() > 0);
+        let mut buffer = with_contents(b"complete");
+        buffer.writer().reserve_for_read(Some(8));
+        assert_eq!(buffer.capacity_in(), 0);
+    }
+    #[test]
+    fn advance_to() {
+        let mut a = with_contents(b"version:2;body");
+        let mut b = a.clone();
+        let offset = a.iter().position(|b| *b == b';').unwrap() + 1;
+        a.reader().advance_to(offset);
+        b.reader().consume(offset);
+        assert_eq!(&a[..], b"body");
+        assert_eq!(&a[..], &b[..]);
+        a.reader().advance_to(0);
+        assert_eq!(&a[..], b"body");
+    }
+    #[test]
+    #[should_panic]
+    fn advance_to_past_end() {
+        with_contents(b"abc").reader().advance_to(4);
+    }
+    #[test]
+    fn normalize_newlines() {
+        let mut buffer = with_contents(b"a\r\nb\nc\rd\r\n\n");
+        assert_eq!(buffer.reader().normalize_newlines(Newline::Lf), Ok(9));
+        assert_eq!(&buffer[..], b"a\nb\nc\rd\n\n");
+        assert_eq!(buffer.reader().normalize_newlines(Newline::CrLf), Ok(13));
+        assert_eq!(&buffer[..], b"a\r\nb\r\nc\rd\r\n\r\n");
+        assert_eq!(buffer.reader().normalize_newlines(Newline::CrLf), Ok(13));
+        assert_eq!(&buffer[..], b"a\r\nb\r\nc\rd\r\n\r\n");
+    }
+    #[test]
+    fn normalize_newlines_boundary() {
+        let mut buffer = with_contents(b"xx\nline\r");
+        buffer.reader().consume(3);
+        assert_eq!(buffer.reader().normalize_newlines(Newline::Lf), Ok(4));
+        assert_eq!(&buffer[..], b"line\r");
+        buffer.reader().consume(4);
+        assert_eq!(buffer.writer().write_str("\nnext"), 5);
+        assert_eq!(buffer.reader().normalize_newlines(Newline::Lf), Ok(5));
+        assert_eq!(&buffer[..], b"\nnext");
+    }
+    #[test]
+    fn reserve_aligned() {
+        let mut buffer = with_contents(b"x");
+        let slice = buffer.writer().reserve_aligned(8, 8);
+        assert_eq!(slice.as_ptr() as usize % 8, 0);
+        assert_eq!(slice.len(), 8);
+        slice.copy_from_slice(&0x0123_4567_89AB_CDEFu64.to_ne_bytes());
+        let padding = buffer.len() - 1;
+        assert!(padding < 8);
+        assert_eq!(buffer.writer().advance_and_slice(8), &0x0123_4567_89AB_CDEFu64.to_ne_bytes());
+        assert_eq!(buffer.len(), 1 + padding + 8);
+        assert_eq!(buffer[0], b'x');
+    }
+    #[test]
+    #[should_panic]
+    fn reserve_aligned_bad_align() {
+        Buffer::new().writer().reserve_aligned(4, 3);
+    }
+    #[test]
+    fn reserve_aligned_zeroes_padding() {
+        let mut buffer = with_contents(&[b'S'; 64]);
+        buffer.reader().consume_all();
+        assert_eq!(buffer.writer().write_str("x"), 1);
+        assert_eq!(buffer.writer().reserve_aligned(8, 8).len(), 8);
+        assert_eq!(buffer[0], b'x');
+        assert!(buffer[1..].iter().all(|b| *b == 0));
+    }
+    #[test]
+    fn reserve_aligned_failure() {
+        let mut buffer = Buffer::with_capacity(4096);
+        assert!(buffer.writer().reserve_aligned(8, 1 << 62).is_empty());
+        assert_eq!(buffer.len(), 0);
+        assert_eq!(buffer.capacity_in(), 4096);
+        let mut buffer = Buffer::with_capacity_in(8, NoGrow);
+        assert_eq!(buffer.writer().write_str("x"), 1);
+        assert!(buffer.writer().reserve_aligned(8, 8).is_empty());
+        assert_eq!(&buffer[..], b"x");
+        assert_eq!(buffer.capacity_in(), 7);
+    }
+    #[test]
+    fn parse_hinted() {
+        fn length_prefixed(bytes: &[u8]) -> ParseStatus<(&[u8], usize), ()> {
+            let Some((len, rest)) = bytes.split_first() else {
+                return ParseStatus::Incomplete(1);
+            };
+            let len = *len as usize;
+            match rest.get(..len) {
+                Some(payload) => ParseStatus::Done((payload, len + 1)),
+                None => ParseStatus::Incomplete(len - rest.len()),
+            }
+        }
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.reader().parse_hinted(length_prefixed), ParseStatus::Incomplete(1));
+        buffer.writer().slice_mut(3)[..3].copy_from_slice(&[20, b'a', b'b']);
+        buffer.writer().advance(3);
+        let ParseStatus::Incomplete(needed) = buffer.reader().parse_hinted(length_prefixed) else {
+            panic!("expected incomplete");
+        };
+        assert_eq!(needed, 18);
+        assert_eq!(buffer.len(), 3);
+        buffer.writer().reserve(needed);
+        assert!(buffer.capacity_in() >= needed);
+        buffer.writer().slice_mut(needed)[..needed].fill(b'c');
+        buffer.writer().advance(needed);
+        let ParseStatus::Done(payload) = buffer.reader().parse_hinted(length_prefixed) else {
+            panic!("expected done");
+        };
+        assert_eq!(payload.len(), 20);
+        assert!(buffer.is_empty());
+    }
+    #[test]
+    fn view_accessors() {
+        let mut buffer = with_contents(b"abcd");
+        buffer.reader().consume(1);
+        let (len, capacity, capacity_in, capacity_front) =
+            (buffer.len(), buffer.capacity(), buffer.capacity_in(), buffer.capacity_front());
+        let reader = buffer.reader();
+        assert_eq!(reader.len(), len);
+        assert!(!reader.is_empty());
+        assert_eq!(reader.capacity(), capacity);
+        assert_eq!(reader.capacity_in(), capacity_in);
+        assert_eq!(reader.capacity_front(), capacity_front);
+        let writer = buffer.writer();
+        assert_eq!(writer.len(), len);
+        assert!(!writer.is_empty());
+        assert_eq!(writer.capacity(), capacity);
+        assert_eq!(writer.capacity_in(), capacity_in);
+        assert_eq!(writer.capacity_front(), capacity_front);
+    }
+    #[test]
+    #[should_panic(expected = "tried to consume 5 bytes, but only 4 are available for output")]
+    fn over_consume() {
+        with_contents(b"abcd").reader().consume(5);
+    }
+    #[test]
+    #[should_panic(expected = "tried to advance by 9 bytes, but only 8 are available for input")]
+    fn over_advance() {
+        Buffer::with_capacity(8).writer().advance(9);
+    }
+    #[test]
+    fn next_chunk() {
+        let mut buffer = with_contents(b"0123456789");
+        let reader = buffer.reader();
+        assert_eq!(reader.next_chunk(4), Some(&b"0123"[..]));
+        assert_eq!(reader.next_chunk(4), Some(&b"4567"[..]));
+        assert_eq!(reader.next_chunk(4), Some(&b"89"[..]));
+        assert_eq!(reader.next_chunk(4), None);
+        assert!(buffer.is_empty());
+    }
+    #[test]
+    fn matches_at() {
+        let mut buffer = with_contents(b"xxHTTP/1.1 200");
+        buffer.reader().consume(2);
+        let reader = buffer.reader();
+        assert!(reader.matches_at(0, b"HTTP/"));
+        assert!(reader.matches_at(5, b"1.1"));
+        assert!(!reader.matches_at(5, b"2.0"));
+        assert!(!reader.matches_at(9, b" 2000"));
+        assert!(reader.matches_at(12, b""));
+        assert!(!reader.matches_at(13, b""));
+    }
+    /// Allocator for which every allocation fails.
+    struct Failing;
+
+    unsafe impl GlobalAlloc for Failing {
+        unsafe fn alloc(&self, _: Layout) -> *mut u8 {
+            core::ptr::null_mut()
+        }
+        unsafe fn dealloc(&self, _: *mut u8, _: Layout) {
+            unreachable!("nothing was allocated")
+        }
+    }
+    /// Allocator for which the initial allocation succeeds but growing it fails.
+    struct NoGrow;
+
+    unsafe impl GlobalAlloc for NoGrow {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            alloc::alloc::alloc(layout)
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            alloc::alloc::dealloc(ptr, layout)
+        }
+        unsafe fn realloc(&self, _: *mut u8, _: Layout, _: usize) -> *mut u8 {
+            core::ptr::null_mut()
+        }
+    }
+
+    #[test]
+    fn try_reserve() {
+        assert_eq!(Buffer::try_with_capacity(isize::MAX as usize + 1).err(), Some(AllocFailure));
+        assert_eq!(Buffer::try_with_capacity(64).unwrap().capacity(), 64);
+        assert!(Buffer::try_with_capacity_in(64, Failing).is_err());
+        assert!(Buffer::try_with_capacity_in(0, Failing).is_ok());
+        assert_eq!(Buffer::new_in(Failing).writer().try_reserve(1), Err(AllocFailure));
+        let mut buffer = with_contents(b"abcd");
+        buffer.reader().consume(1);
+        assert_eq!(buffer.writer().try_reserve(usize::MAX), Err(AllocFailure));
+        assert_eq!(buffer.writer().try_reserve(isize::MAX as usize - 2), Err(AllocFailure));
+        assert_eq!(&buffer[..], b"bcd");
+        assert_eq!(buffer.writer().try_reserve(100), Ok(()));
+        assert!(buffer.capacity_in() >= 100);
+        assert_eq!(&buffer[..], b"bcd");
+    }
+    #[test]
+    fn write_repeated() {
+        let tracking = Tracking::default();
+        let mut buffer = Buffer::with_capacity_in(1, &tracking);
+        assert_eq!(buffer.writer().write_str("x"), 1);
+        assert_eq!(tracking.counts(), (1, 0, 0));
+        assert_eq!(buffer.writer().write_repeated(0x20, 4096), 4096);
+        // The whole fill needs a single growth, rather than one per chunk or byte.
+        assert_eq!(tracking.counts(), (1, 1, 0));
+        assert_eq!(buffer.len(), 4097);
+        assert_eq!(buffer[0], b'x');
+        assert!(buffer[1..].iter().all(|b| *b == 0x20));
+        assert_eq!(buffer.writer().write_repeated(0x20, 0), 0);
+        assert_eq!(tracking.counts(), (1, 1, 0));
+        let mut buffer = Buffer::new_in(&tracking);
+        assert_eq!(buffer.writer().write_repeated(0x20, 4096), 4096);
+        assert_eq!(tracking.counts(), (2, 1, 0));
+        assert_eq!(Buffer::new_in(Failing).writer().write_repeated(0, 8), 0);
+    }
+    #[test]
+    fn reserve_guarantee() {
+        let mut buffer = Buffer::new();
+        for n in [1, 7, 64, 65, 1000, 4096] {
+            buffer.writer().reserve(n);
+            assert!(buffer.capacity_in() >= n);
+            let half = buffer.capacity_in() / 2;
+            buffer.writer().advance(half);
+        }
+        let tracking = Tracking::default();
+        let mut buffer = Buffer::new_in(&tracking);
+        buffer.writer().reserve(0);
+        assert_eq!(buffer.capacity(), 0);
+        let mut buffer = Buffer::with_capacity_in(8, &tracking);
+        buffer.writer().advance(8);
+        buffer.writer().reserve(0);
+        assert_eq!(buffer.capacity(), 8);
+        assert_eq!(tracking.counts(), (1, 0, 0));
+    }
+    #[test]
+    fn reserve_growth() {
+        let tracking = Tracking::default();
+        let mut buffer = Buffer::new_in(&tracking);
+        let mut capacities = [0usize; 32];
+        for _ in 0..10000 {
+            // Slow consumer: the amount of buffered data grows every cycle.
+            buffer.writer().reserve(4);
+            buffer.writer().advance(4);
+            buffer.reader().consume(1);
+            let growths = tracking.allocs.get() + tracking.reallocs.get();
+            capacities[growths] = buffer.capacity();
+        }
+        let growths = tracking.allocs.get() + tracking.reallocs.get();
+        assert!(growths <= 16, "{growths} reallocations for 30000 bytes");
+        for pair in capacities[1..=growths].windows(2) {
+            assert!(pair[1] >= pair[0] * 2);
+        }
+    }
+    #[test]
+    fn try_parse_many() {
+        fn record(bytes: &[u8]) -> ParseStatus<(u64, usize), ()> {
+            match bytes.get(..8) {
+                Some(&[0xFF, ..]) => ParseStatus::Err(()),
+                Some(record) => {
+                    ParseStatus::Done((u64::from_be_bytes(record.try_into().unwrap()), 8))
+                }
+                None => ParseStatus::Incomplete(8 - bytes.len()),
+            }
+        }
+        let mut buffer = Buffer::new();
+        for n in 1..=5u64 {
+            buffer.writer().slice_mut(8)[..8].copy_from_slice(&n.to_be_bytes());
+            buffer.writer().advance(8);
+        }
+        assert_eq!(buffer.writer().write_repeated(0, 3), 3);
+        let mut records = Vec::new();
+        assert_eq!(buffer.reader().try_parse_many(&mut records, record), Ok(5));
+        assert_eq!(records, [1, 2, 3, 4, 5]);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.reader().try_parse_many(&mut records, record), Ok(0));
+        assert_eq!(buffer.writer().write_repeated(0, 5), 5);
+        assert_eq!(buffer.writer().write_repeated(0xFF, 8), 8);
+        assert_eq!(buffer.reader().try_parse_many(&mut records, record), Err(()));
+        assert_eq!(records, [1, 2, 3, 4, 5, 0]);
+        assert_eq!(buffer.len(), 8);
+    }
+    #[test]
+    fn allocations_balance() {
+        let tracking = Tracking::default();
+        {
+            let _empty = Buffer::new_in(&tracking);
+            let _zero = Buffer::with_capacity_in(0, &tracking);
+            let mut grown = Buffer::with_capacity_in(4, &tracking);
+            assert_eq!(grown.writer().write_repeated(1, 100), 100);
+            let _clone = grown.clone();
+            let mut shrunk = grown.clone();
+            shrunk.reader().consume(60);
+            shrunk.shrink_to_fit(0);
+            assert_eq!(shrunk.capacity(), 40);
+            let mut emptied = grown.clone();
+            emptied.reader().consume_all();
+            emptied.shrink_to_fit(0);
+            assert_eq!(emptied.capacity(), 0);
+            let mut regrown = emptied.clone();
+            regrown.writer().reserve(16);
+            let mut swapped = Buffer::with_capacity_in(8, &tracking);
+            swapped.swap(&mut grown);
+            let _replaced = swapped.replace(Buffer::new_in(&tracking));
+            let failed = Buffer::try_with_capacity_in(isize::MAX as usize + 1, &tracking);
+            assert!(failed.is_err());
+        }
+        let (allocs, reallocs, deallocs) = tracking.counts();
+        assert!(allocs > 0);
+        assert!(reallocs > 0);
+        assert_eq!(allocs, deallocs);
+    }
+    #[test]
+    fn parse_incremental() {
+        use core::task::Poll;
+        fn line(bytes: &[u8]) -> Poll<Result<(&[u8], usize), ()>> {
+            match bytes.iter().position(|b| *b == b'\n') {
+                Some(0) => Poll::Ready(Err(())),
+                Some(idx) => Poll::Ready(Ok((&bytes[..idx], idx + 1))),
+                None => Poll::Pending,
+            }
+        }
+        let mut buffer = with_contents(b"partial");
+        assert_eq!(buffer.reader().parse_incremental(line), Poll::Pending);
+        assert_eq!(&buffer[..], b"partial");
+        assert_eq!(buffer.writer().write_str(" line\n\n"), 7);
+        assert_eq!(buffer.reader().parse_incremental(line), Poll::Ready(Ok(&b"partial line"[..])));
+        assert_eq!(buffer.reader().parse_incremental(line), Poll::Ready(Err(())));
+        assert_eq!(&buffer[..], b"\n");
+    }
+    #[test]
+    fn commit_from() {
+        let mut buffer = with_contents(b"stale data");
+        buffer.reader().consume_all();
+        let len = buffer.writer().commit_from(4, |slice| {
+            assert_eq!(slice, &[0; 4]);
+            slice.copy_from_slice(b"abcd");
+        });
+        assert_eq!(len, 4);
+        assert_eq!(&buffer[..], b"abcd");
+        let written = buffer.writer().try_commit_from(8, |slice| {
+            slice[..3].copy_from_slice(b"efg");
+            Ok::<_, ()>(3)
+        });
+        assert_eq!(written, Ok(3));
+        assert_eq!(&buffer[..], b"abcdefg");
+        assert_eq!(buffer.writer().try_commit_from(8, |_| Err(())), Err(()));
+        assert_eq!(&buffer[..], b"abcdefg");
+    }
+    #[test]
+    fn fmt_write() {
+        use core::fmt::Write;
+        let mut buffer = Buffer::new();
+        let nick = "tincan";
+        write!(buffer.writer(), "NICK {nick}\r\n").unwrap();
+        assert_eq!(&buffer[..], b"NICK tincan\r\n");
+        let mut buffer = Buffer::new_in(Failing);
+        assert!(write!(buffer.writer(), "{}", 12345).is_err());
+        assert!(buffer.is_empty());
+        assert!(write!(buffer.writer(), "").is_ok());
+    }
+    #[test]
+    fn vec_conversion() {
+        let mut buffer = with_contents(b"discard:keep");
+        buffer.reader().consume(8);
+        let ptr = buffer.reader().slice().as_ptr() as usize - 8;
+        let capacity = buffer.capacity();
+        let vec = buffer.into_vec();
+        assert_eq!(vec, b"keep");
+        assert_eq!(vec.as_ptr() as usize, ptr);
+        assert_eq!(vec.capacity(), capacity);
+
+        let mut vec = Vec::with_capacity(32);
+        vec.extend_from_slice(b"data");
+        let ptr = vec.as_ptr();
+        let mut buffer = Buffer::from(vec);
+        assert_eq!(&buffer[..], b"data");
+        assert_eq!(buffer.capacity(), 32);
+        assert_eq!(buffer.capacity_in(), 28);
+        assert_eq!(buffer.writer().zeroed_slice(28).as_ptr(), ptr.wrapping_add(4));
+        assert_eq!(buffer.writer().write_repeated(b'!', 40), 40);
+        assert_eq!(Vec::from(buffer).len(), 44);
+
+        assert!(Buffer::new().into_vec().is_empty());
+        let empty = Buffer::from_vec(Vec::new());
+        assert_eq!(empty.capacity(), 0);
+        assert_eq!(empty.into_vec().capacity(), 0);
+    }
+    #[test]
+    fn delimiters() {
+        use core::task::Poll;
+        let mut buffer = with_contents(b"\r\nNICK foo\r\nPING");
+        assert_eq!(buffer.reader().find_delimiter(b"\r\n"), Some(2));
+        assert_eq!(buffer.reader().find_delimiter(b""), Some(0));
+        assert_eq!(buffer.reader().find_delimiter(b"PONG"), None);
+        let r = buffer.reader().parse_delimited(b"\r\n", |line| Ok::<_, ()>(line.len()));
+        assert_eq!(r, Poll::Ready(Ok(2)));
+        let r = buffer.reader().parse_delimited(b"\r\n", Ok::<_, ()>);
+        assert_eq!(r, Poll::Ready(Ok(&b"NICK foo\r\n"[..])));
+        let r = buffer.reader().parse_delimited(b"\r\n", |_| Ok::<(), ()>(()));
+        assert_eq!(r, Poll::Pending);
+        assert_eq!(&buffer[..], b"PING");
+
+        // Delimiter split across two writes, landing at the very end.
+        assert_eq!(buffer.writer().write_str(" x\r"), 3);
+        assert_eq!(buffer.reader().find_delimiter(b"\r\n"), None);
+        assert_eq!(buffer.writer().write_str("\n"), 1);
+        assert_eq!(buffer.reader().find_delimiter(b"\r\n"), Some(buffer.len()));
+        let r = buffer.reader().parse_delimited(b"\r\n", |_| Err::<(), _>("bad"));
+        assert_eq!(r, Poll::Ready(Err("bad")));
+        assert_eq!(&buffer[..], b"PING x\r\n");
+        let r = buffer.reader().parse_delimited(b"\r\n", |line| Ok::<_, ()>(line.len()));
+        assert_eq!(r, Poll::Ready(Ok(8)));
+        assert!(buffer.is_empty());
+    }
+    #[test]
+    fn write_str() {
+        let mut buffer = Buffer::new();
+        assert_eq!(buffer.writer().write_str("h\u{e9}llo"), 6);
+        assert_eq!(&buffer[..], "h\u{e9}llo".as_bytes());
+        let s = "a\u{e9}\u{1F980}";
+        for (max, expected) in [0, 1, 1, 3, 3, 3, 3, 7, 7].into_iter().enumerate() {
+            let len = utf8_prefix_len(s, max);
+            assert_eq!(len, expected);
+            assert!(core::str::from_utf8(&s.as_bytes()[..len]).is_ok());
+        }
+        // The buffer cannot grow, so only "a\u{e9}" fits before the 4-byte code point.
+        let mut buffer = Buffer::with_capacity_in(5, NoGrow);
+        assert_eq!(buffer.writer().write_str(s), 3);
+        assert_eq!(core::str::from_utf8(&buffer[..]), Ok("a\u{e9}"));
+        assert_eq!(buffer.capacity_in(), 2);
+    }
     #[cfg(feature = "std")]
     fn io_test(in_rate: usize, out_rate: usize) {
         use std::io::Cursor;
@@ -478,6 +2125,112 @@ mod tests {
         }
         assert_eq!(read.into_inner(), write.into_inner());
     }
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes() {
+        use bytes::{Buf, BufMut};
+        let mut buffer = Buffer::new();
+        let writer = buffer.writer();
+        writer.put_u16(0xABCD);
+        writer.put_slice(&[7; 100]);
+        writer.put_u32_le(42);
+        assert_eq!(buffer.len(), 106);
+        let reader = buffer.reader();
+        assert_eq!(reader.remaining(), 106);
+        assert_eq!(reader.get_u16(), 0xABCD);
+        assert_eq!(reader.chunk().len(), 104);
+        reader.advance(100);
+        assert_eq!(reader.get_u32_le(), 42);
+        assert!(!reader.has_remaining());
+    }
+    #[cfg(feature = "bytes")]
+    #[test]
+    #[should_panic = "failed to reserve"]
+    fn bytes_alloc_failure() {
+        use bytes::BufMut;
+        Buffer::new_in(Failing).writer().put_slice(b"abc");
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_lines() {
+        use std::io::{BufRead, Cursor, Write};
+        let mut buffer = Buffer::new();
+        for idx in 0..1000 {
+            writeln!(buffer, "line {idx}").unwrap();
+        }
+        buffer.write_all(b"no trailing newline").unwrap();
+        let mut expected = Cursor::new(buffer.to_vec());
+        let (mut line, mut expected_line) = (String::new(), String::new());
+        loop {
+            line.clear();
+            expected_line.clear();
+            let len = buffer.read_line(&mut line).unwrap();
+            assert_eq!(len, expected.read_line(&mut expected_line).unwrap());
+            assert_eq!(line, expected_line);
+            if len == 0 {
+                break;
+            }
+        }
+        buffer.write_all(b"a,b,,c").unwrap();
+        let mut fields = Vec::new();
+        while buffer.reader().read_until(b',', &mut fields).unwrap() != 0 {}
+        assert_eq!(fields, b"a,b,,c");
+        buffer.write_all(b"\xFF\nok\n").unwrap();
+        assert!(buffer.read_line(&mut line).is_err());
+        assert_eq!(&buffer[..], b"ok\n");
+    }
+    /// Reader and writer that transfer at most `limit` bytes per call.
+    #[cfg(feature = "std")]
+    struct Trickle<T> {
+        inner: T,
+        limit: usize,
+    }
+    #[cfg(feature = "std")]
+    impl<T: std::io::Read> std::io::Read for Trickle<T> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = core::cmp::min(buf.len(), self.limit);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+    #[cfg(feature = "std")]
+    impl<T: std::io::Write> std::io::Write for Trickle<T> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let len = core::cmp::min(buf.len(), self.limit);
+            self.inner.write(&buf[..len])
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_all() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut buffer = Buffer::new();
+        let mut read = Trickle { inner: &data[..], limit: 7 };
+        assert_eq!(buffer.writer().read_from_all(16, &mut read).unwrap(), 256);
+        assert_eq!(&buffer[..], &data[..]);
+        let mut write = Trickle { inner: Vec::new(), limit: 5 };
+        assert_eq!(buffer.reader().write_to_all(&mut write).unwrap(), 256);
+        assert!(buffer.is_empty());
+        assert_eq!(write.inner, data);
+        assert_eq!(buffer.writer().write_str("stuck"), 5);
+        let mut write = Trickle { inner: Vec::new(), limit: 0 };
+        let err = buffer.reader().write_to_all(&mut write).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+
+        // A full buffer still grows when min is 0.
+        let mut buffer = with_contents(b"full");
+        assert_eq!(buffer.writer().read_from_all(0, &mut &data[..]).unwrap(), 256);
+        assert_eq!(buffer.len(), 260);
+        let mut buffer = Buffer::new_in(Failing);
+        let err = buffer.writer().read_from_all(16, &mut &data[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+        let mut buffer = Buffer::with_capacity_in(8, NoGrow);
+        let err = buffer.writer().read_from_all(0, &mut &data[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+        assert_eq!(&buffer[..], &data[..8]);
+    }
     #[cfg(feature = "std")]
     #[test]
     fn equal_rates() {